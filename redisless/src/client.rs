@@ -0,0 +1,59 @@
+//! A minimal RESP client over the in-memory `DuplexStream`, used by
+//! `Server::connect_in_process` and `mock::ScriptedMock` so tests can drive
+//! a connection without a TCP socket or the `redis` crate.
+
+use std::io::{self, BufReader, Write};
+
+use crate::duplex::DuplexStream;
+use crate::resp::{read_reply, RespValue};
+
+/// A connection to a `Server` (or a `ScriptedMock`) over an in-memory
+/// duplex pipe instead of TCP.
+pub struct InProcessConnection {
+    reader: BufReader<DuplexStream>,
+    writer: DuplexStream,
+}
+
+impl InProcessConnection {
+    pub(crate) fn new(stream: DuplexStream) -> Self {
+        InProcessConnection {
+            reader: BufReader::new(stream.clone()),
+            writer: stream,
+        }
+    }
+
+    /// Sends one command and blocks for its reply.
+    pub fn command(&mut self, args: &[&[u8]]) -> io::Result<RespValue> {
+        let request = RespValue::Array(
+            args.iter()
+                .map(|arg| RespValue::BulkString(arg.to_vec()))
+                .collect(),
+        );
+        request.write_to(&mut self.writer)?;
+        self.writer.flush()?;
+        read_reply(&mut self.reader)
+    }
+
+    /// Writes already-encoded RESP bytes as-is, for callers (e.g. a
+    /// `redis::ConnectionLike` adapter in tests) that pack their own
+    /// requests instead of building a `RespValue`. Only used by tests.
+    #[cfg(test)]
+    pub(crate) fn send_packed(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.writer.flush()
+    }
+
+    /// Reads one reply without sending anything first, the other half of
+    /// `send_packed` for callers that write a batch of pipelined commands
+    /// and then read back one reply per command. Only used by tests.
+    #[cfg(test)]
+    pub(crate) fn read_one(&mut self) -> io::Result<RespValue> {
+        read_reply(&mut self.reader)
+    }
+}
+
+impl Drop for InProcessConnection {
+    fn drop(&mut self) {
+        self.writer.shutdown();
+    }
+}
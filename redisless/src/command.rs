@@ -0,0 +1,222 @@
+//! Parses raw RESP argument arrays into the commands the server understands.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Get(String),
+    Set(String, String),
+    Incr(String, i64),
+    Decr(String, i64),
+    Del(String),
+    MGet(Vec<String>),
+    MSet(Vec<(String, String)>),
+    /// `KEYS pattern`, a glob-matching snapshot enumeration of all keys.
+    Keys(String),
+    /// `SCAN cursor [MATCH pattern] [COUNT n]`.
+    Scan {
+        cursor: usize,
+        pattern: Option<String>,
+        count: usize,
+    },
+    /// `RGKEYS pattern` — like `KEYS`, but `pattern` is a regular expression.
+    RgKeys(String),
+    /// `RGVALUES pattern` — the values of every key matching the regex.
+    RgValues(String),
+    /// `RGDELETE pattern` — deletes every key matching the regex, returning
+    /// the number of keys removed.
+    RgDelete(String),
+    /// `EXPIRE key seconds`.
+    Expire(String, i64),
+    /// `PEXPIRE key milliseconds`.
+    Pexpire(String, i64),
+    /// `TTL key`, in whole seconds (`-2` missing, `-1` no TTL set).
+    Ttl(String),
+    /// `PTTL key`, in milliseconds.
+    Pttl(String),
+    Persist(String),
+    /// `SETEX key seconds value`.
+    Setex(String, i64, String),
+    /// Starts queuing subsequent commands on this connection instead of
+    /// running them immediately.
+    Multi,
+    /// Runs every command queued since `MULTI` and returns their replies as
+    /// one array.
+    Exec,
+    /// Drops the commands queued since `MULTI` without running them.
+    Discard,
+    /// Synchronously writes the current keyspace to the configured
+    /// snapshot file.
+    Save,
+    /// Writes the snapshot on a background thread and replies immediately.
+    Bgsave,
+    Subscribe(Vec<String>),
+    PSubscribe(Vec<String>),
+    Unsubscribe(Vec<String>),
+    Publish(String, Vec<u8>),
+}
+
+const DEFAULT_SCAN_COUNT: usize = 10;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CommandError(pub String);
+
+impl Command {
+    /// Builds a `Command` from the raw arguments of a single RESP request,
+    /// e.g. `["SET", "foo", "bar"]`.
+    pub fn parse(args: Vec<Vec<u8>>) -> Result<Command, CommandError> {
+        let mut args = args.into_iter();
+        let name = args
+            .next()
+            .ok_or_else(|| CommandError("empty command".to_string()))?;
+        let name = String::from_utf8_lossy(&name).to_ascii_uppercase();
+
+        match name.as_str() {
+            "GET" => Ok(Command::Get(next_string(&mut args)?)),
+            "SET" => {
+                let key = next_string(&mut args)?;
+                let value = next_string(&mut args)?;
+                Ok(Command::Set(key, value))
+            }
+            "INCR" | "INCRBY" => {
+                let key = next_string(&mut args)?;
+                let delta = match args.next() {
+                    Some(raw) => parse_i64(&raw)?,
+                    None => 1,
+                };
+                Ok(Command::Incr(key, delta))
+            }
+            "DECR" | "DECRBY" => {
+                let key = next_string(&mut args)?;
+                let delta = match args.next() {
+                    Some(raw) => parse_i64(&raw)?,
+                    None => 1,
+                };
+                Ok(Command::Decr(key, delta))
+            }
+            "DEL" => Ok(Command::Del(next_string(&mut args)?)),
+            "MGET" => {
+                let keys = remaining_strings(args)?;
+                Ok(Command::MGet(keys))
+            }
+            "MSET" => {
+                let values: Vec<String> =
+                    args.map(|raw| String::from_utf8_lossy(&raw).to_string()).collect();
+                if values.is_empty() || !values.len().is_multiple_of(2) {
+                    return Err(CommandError(
+                        "MSET requires an even number of key/value arguments".to_string(),
+                    ));
+                }
+                let pairs = values
+                    .chunks_exact(2)
+                    .map(|pair| (pair[0].clone(), pair[1].clone()))
+                    .collect();
+                Ok(Command::MSet(pairs))
+            }
+            "KEYS" => Ok(Command::Keys(next_string(&mut args)?)),
+            "SCAN" => {
+                let cursor = parse_cursor(&args.next().ok_or_else(|| {
+                    CommandError("SCAN requires a cursor".to_string())
+                })?)?;
+                let mut pattern = None;
+                let mut count = DEFAULT_SCAN_COUNT;
+                while let Some(raw) = args.next() {
+                    match String::from_utf8_lossy(&raw).to_ascii_uppercase().as_str() {
+                        "MATCH" => pattern = Some(next_string(&mut args)?),
+                        "COUNT" => {
+                            count = parse_count(&args.next().ok_or_else(|| {
+                                CommandError("COUNT requires a value".to_string())
+                            })?)?
+                        }
+                        other => return Err(CommandError(format!("unknown SCAN option '{}'", other))),
+                    }
+                }
+                Ok(Command::Scan { cursor, pattern, count })
+            }
+            "RGKEYS" => Ok(Command::RgKeys(next_string(&mut args)?)),
+            "RGVALUES" => Ok(Command::RgValues(next_string(&mut args)?)),
+            "RGDELETE" => Ok(Command::RgDelete(next_string(&mut args)?)),
+            "EXPIRE" => {
+                let key = next_string(&mut args)?;
+                let seconds = parse_i64(&args.next().ok_or_else(|| {
+                    CommandError("EXPIRE requires a seconds argument".to_string())
+                })?)?;
+                Ok(Command::Expire(key, seconds))
+            }
+            "PEXPIRE" => {
+                let key = next_string(&mut args)?;
+                let millis = parse_i64(&args.next().ok_or_else(|| {
+                    CommandError("PEXPIRE requires a milliseconds argument".to_string())
+                })?)?;
+                Ok(Command::Pexpire(key, millis))
+            }
+            "TTL" => Ok(Command::Ttl(next_string(&mut args)?)),
+            "PTTL" => Ok(Command::Pttl(next_string(&mut args)?)),
+            "PERSIST" => Ok(Command::Persist(next_string(&mut args)?)),
+            "SETEX" => {
+                let key = next_string(&mut args)?;
+                let seconds = parse_i64(&args.next().ok_or_else(|| {
+                    CommandError("SETEX requires a seconds argument".to_string())
+                })?)?;
+                let value = next_string(&mut args)?;
+                Ok(Command::Setex(key, seconds, value))
+            }
+            "MULTI" => Ok(Command::Multi),
+            "EXEC" => Ok(Command::Exec),
+            "DISCARD" => Ok(Command::Discard),
+            "SAVE" => Ok(Command::Save),
+            "BGSAVE" => Ok(Command::Bgsave),
+            "SUBSCRIBE" => Ok(Command::Subscribe(remaining_strings(args)?)),
+            "PSUBSCRIBE" => Ok(Command::PSubscribe(remaining_strings(args)?)),
+            "UNSUBSCRIBE" => Ok(Command::Unsubscribe(remaining_strings(args)?)),
+            "PUBLISH" => {
+                let channel = next_string(&mut args)?;
+                let payload = args
+                    .next()
+                    .ok_or_else(|| CommandError("PUBLISH requires a payload".to_string()))?;
+                Ok(Command::Publish(channel, payload))
+            }
+            other => Err(CommandError(format!("unknown command '{}'", other))),
+        }
+    }
+}
+
+fn next_string(args: &mut impl Iterator<Item = Vec<u8>>) -> Result<String, CommandError> {
+    args.next()
+        .map(|raw| String::from_utf8_lossy(&raw).to_string())
+        .ok_or_else(|| CommandError("missing argument".to_string()))
+}
+
+fn remaining_strings(
+    args: impl Iterator<Item = Vec<u8>>,
+) -> Result<Vec<String>, CommandError> {
+    let names: Vec<String> = args
+        .map(|raw| String::from_utf8_lossy(&raw).to_string())
+        .collect();
+    if names.is_empty() {
+        return Err(CommandError("requires at least one argument".to_string()));
+    }
+    Ok(names)
+}
+
+fn parse_i64(raw: &[u8]) -> Result<i64, CommandError> {
+    std::str::from_utf8(raw)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| CommandError("value is not an integer".to_string()))
+}
+
+/// `SCAN`'s cursor: must be non-negative, since it's really an insertion
+/// sequence number cast to `usize` once parsed.
+fn parse_cursor(raw: &[u8]) -> Result<usize, CommandError> {
+    usize::try_from(parse_i64(raw)?)
+        .map_err(|_| CommandError("cursor must be a non-negative integer".to_string()))
+}
+
+/// `SCAN`'s `COUNT`: must be positive, since it's used as a `Vec` capacity
+/// hint — a negative value cast to `usize` would overflow it.
+fn parse_count(raw: &[u8]) -> Result<usize, CommandError> {
+    let count = parse_i64(raw)?;
+    usize::try_from(count)
+        .ok()
+        .filter(|&count| count > 0)
+        .ok_or_else(|| CommandError("COUNT must be a positive integer".to_string()))
+}
@@ -0,0 +1,112 @@
+//! An in-memory, blocking duplex byte pipe, used so a connection can be
+//! handled without binding a real TCP socket (`Server::connect_in_process`,
+//! `mock::ScriptedMock`).
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::transport::Transport;
+
+struct PipeState {
+    buffer: VecDeque<u8>,
+    closed: bool,
+}
+
+struct Pipe {
+    state: Mutex<PipeState>,
+    ready: Condvar,
+}
+
+impl Pipe {
+    fn new() -> Self {
+        Pipe {
+            state: Mutex::new(PipeState {
+                buffer: VecDeque::new(),
+                closed: false,
+            }),
+            ready: Condvar::new(),
+        }
+    }
+
+    fn write(&self, bytes: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        state.buffer.extend(bytes);
+        self.ready.notify_all();
+    }
+
+    /// Blocks until at least one byte is available or the pipe is closed,
+    /// then copies as much as fits into `buf`. Returns `0` only once the
+    /// pipe is closed and drained, mirroring a socket's EOF.
+    fn read(&self, buf: &mut [u8]) -> usize {
+        let mut state = self.state.lock().unwrap();
+        while state.buffer.is_empty() && !state.closed {
+            state = self.ready.wait(state).unwrap();
+        }
+        let n = buf.len().min(state.buffer.len());
+        for slot in buf[..n].iter_mut() {
+            *slot = state.buffer.pop_front().unwrap();
+        }
+        n
+    }
+
+    fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        self.ready.notify_all();
+    }
+}
+
+/// One end of an in-memory duplex connection. `pair()` returns the two
+/// ends; each reads what the other writes, the same way a `TcpStream`
+/// connected to a loopback listener would.
+#[derive(Clone)]
+pub(crate) struct DuplexStream {
+    read_pipe: Arc<Pipe>,
+    write_pipe: Arc<Pipe>,
+}
+
+impl DuplexStream {
+    pub(crate) fn pair() -> (DuplexStream, DuplexStream) {
+        let a = Arc::new(Pipe::new());
+        let b = Arc::new(Pipe::new());
+        (
+            DuplexStream {
+                read_pipe: a.clone(),
+                write_pipe: b.clone(),
+            },
+            DuplexStream {
+                read_pipe: b,
+                write_pipe: a,
+            },
+        )
+    }
+
+    /// Signals EOF to whatever is reading the other end.
+    pub(crate) fn shutdown(&self) {
+        self.write_pipe.close();
+    }
+}
+
+impl Read for DuplexStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.read_pipe.read(buf))
+    }
+}
+
+impl Write for DuplexStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_pipe.write(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for DuplexStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(self.clone())
+    }
+}
@@ -0,0 +1,124 @@
+//! Redis-style glob matching (`*`, `?`, `[...]`), shared by pub/sub pattern
+//! subscriptions and the `KEYS`/`SCAN` key-enumeration commands.
+
+/// Returns whether `text` matches the glob `pattern`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches(&pattern, &text)
+}
+
+/// One glob pattern element: either a `*` wildcard or something that
+/// consumes exactly one character of text.
+enum Token {
+    Star,
+    Any,
+    Literal(char),
+    /// The raw contents of a `[...]` class (including a leading `^` for
+    /// negation, same as what `char_in_class` expects).
+    Class(Vec<char>),
+}
+
+/// Splits `pattern` into tokens, so the matcher below never has to re-scan
+/// a `[...]` class while walking `text`.
+fn tokenize(pattern: &[char]) -> Vec<Token> {
+    let mut tokens = Vec::with_capacity(pattern.len());
+    let mut i = 0;
+    while i < pattern.len() {
+        match pattern[i] {
+            '*' => {
+                tokens.push(Token::Star);
+                i += 1;
+            }
+            '?' => {
+                tokens.push(Token::Any);
+                i += 1;
+            }
+            '[' => match pattern[i + 1..].iter().position(|&c| c == ']') {
+                Some(offset) => {
+                    let close = i + 1 + offset;
+                    tokens.push(Token::Class(pattern[i + 1..close].to_vec()));
+                    i = close + 1;
+                }
+                // No closing ']': Redis treats the '[' itself as a literal.
+                None => {
+                    tokens.push(Token::Literal('['));
+                    i += 1;
+                }
+            },
+            c => {
+                tokens.push(Token::Literal(c));
+                i += 1;
+            }
+        }
+    }
+    tokens
+}
+
+fn token_matches(token: &Token, c: char) -> bool {
+    match token {
+        Token::Any => true,
+        Token::Literal(l) => *l == c,
+        Token::Class(class) => char_in_class(class, c),
+        Token::Star => unreachable!("'*' is consumed separately, never matched against a char"),
+    }
+}
+
+/// Linear two-pointer glob match: track the most recent `*` (if any) and,
+/// on a mismatch, just retry one character further into `text` from there
+/// instead of recursing into both "consume" and "skip" branches. This is
+/// the classic `fnmatch`-style algorithm — unlike the naive recursive
+/// formulation it can't blow up exponentially on adversarial patterns like
+/// `"a*".repeat(n) + "b"`.
+fn matches(pattern: &[char], text: &[char]) -> bool {
+    let tokens = tokenize(pattern);
+
+    let mut ti = 0;
+    let mut pi = 0;
+    // The most recent '*' seen, and how far into `text` we've tried
+    // resuming from after it; backtracking just advances `star_ti`.
+    let mut star: Option<(usize, usize)> = None;
+
+    while ti < text.len() {
+        if matches!(tokens.get(pi), Some(Token::Star)) {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if tokens.get(pi).is_some_and(|t| token_matches(t, text[ti])) {
+            pi += 1;
+            ti += 1;
+        } else if let Some((star_pi, star_ti)) = star {
+            pi = star_pi + 1;
+            ti = star_ti + 1;
+            star = Some((star_pi, ti));
+        } else {
+            return false;
+        }
+    }
+
+    tokens[pi..].iter().all(|t| matches!(t, Token::Star))
+}
+
+fn char_in_class(class: &[char], c: char) -> bool {
+    let (negate, class) = match class.first() {
+        Some('^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            if class[i] <= c && c <= class[i + 2] {
+                found = true;
+            }
+            i += 3;
+        } else {
+            if class[i] == c {
+                found = true;
+            }
+            i += 1;
+        }
+    }
+
+    found != negate
+}
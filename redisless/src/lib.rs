@@ -0,0 +1,18 @@
+mod client;
+mod command;
+mod duplex;
+mod glob;
+pub mod mock;
+mod persistence;
+mod pubsub;
+mod resp;
+pub mod server;
+pub mod storage;
+mod transport;
+pub mod value;
+
+pub use client::InProcessConnection;
+pub use command::Command;
+pub use server::{Server, ServerState};
+pub use storage::{InMemoryStorage, Storage};
+pub use value::{CallError, FromRedisValue, ToRedisArg};
@@ -0,0 +1,50 @@
+//! A scriptable mock connection for testing client code written against
+//! redis-less without running a real `Server`: given an ordered list of
+//! expected `(command, canned reply)` pairs, it checks each incoming
+//! command against the script and writes back the canned RESP reply.
+
+use std::thread;
+
+use crate::client::InProcessConnection;
+use crate::duplex::DuplexStream;
+use crate::resp::{read_command, RespValue};
+
+pub struct ScriptedMock;
+
+impl ScriptedMock {
+    /// Spawns a background thread that serves `script` in order, and
+    /// returns a connection to it.
+    ///
+    /// Each entry is `(expected_command, reply)`, where `expected_command`
+    /// is the raw argument array (e.g. `vec![b"SET".to_vec(), b"k".to_vec(),
+    /// b"v".to_vec()]`). A command that doesn't match the next expected one
+    /// gets an `Error` reply describing the mismatch instead of panicking,
+    /// so the calling test can assert on it.
+    pub fn connect(script: Vec<(Vec<Vec<u8>>, RespValue)>) -> InProcessConnection {
+        let (server_end, client_end) = DuplexStream::pair();
+        thread::spawn(move || Self::serve(server_end, script));
+        InProcessConnection::new(client_end)
+    }
+
+    fn serve(stream: DuplexStream, script: Vec<(Vec<Vec<u8>>, RespValue)>) {
+        let mut reader = std::io::BufReader::new(stream.clone());
+        let mut writer = stream;
+        for (expected_command, reply) in script {
+            let actual_command = match read_command(&mut reader) {
+                Ok(Some(command)) => command,
+                Ok(None) | Err(_) => break,
+            };
+            let outcome = if actual_command == expected_command {
+                reply
+            } else {
+                RespValue::Error(format!(
+                    "ERR mock expected {:?}, got {:?}",
+                    expected_command, actual_command
+                ))
+            };
+            if outcome.write_to(&mut writer).is_err() {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! A compact RDB-style snapshot of the keyspace: a version byte, then
+//! length-prefixed key/value records, then a trailing checksum so a
+//! truncated or corrupted file is rejected instead of silently half-loaded.
+//!
+//! `SAVE`/`BGSAVE` write one of these; `Server::start` loads one back in if
+//! it was started with a snapshot path and the file exists.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const SNAPSHOT_VERSION: u8 = 1;
+
+pub fn save(pairs: &[(Vec<u8>, Vec<u8>)], path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let mut checksum: u32 = SNAPSHOT_VERSION as u32;
+
+    out.write_all(&[SNAPSHOT_VERSION])?;
+    for (key, value) in pairs {
+        write_record(&mut out, key, &mut checksum)?;
+        write_record(&mut out, value, &mut checksum)?;
+    }
+    out.write_all(&checksum.to_be_bytes())?;
+    out.flush()
+}
+
+/// Returns an empty keyspace if `path` doesn't exist yet, which is the
+/// normal case for a server's very first run.
+pub fn load(path: &Path) -> io::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut bytes = Vec::new();
+    BufReader::new(File::open(path)?).read_to_end(&mut bytes)?;
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+    if bytes.len() < 1 + 4 {
+        return Err(invalid_data("snapshot truncated"));
+    }
+
+    let version = bytes[0];
+    if version != SNAPSHOT_VERSION {
+        return Err(invalid_data(format!("unsupported snapshot version {}", version)));
+    }
+
+    let body = &bytes[1..bytes.len() - 4];
+    let expected_checksum = u32::from_be_bytes(bytes[bytes.len() - 4..].try_into().unwrap());
+
+    let mut checksum: u32 = version as u32;
+    let mut pairs = Vec::new();
+    let mut offset = 0;
+    while offset < body.len() {
+        let (key, next) = read_record(body, offset, &mut checksum)?;
+        let (value, next) = read_record(body, next, &mut checksum)?;
+        pairs.push((key, value));
+        offset = next;
+    }
+
+    if checksum != expected_checksum {
+        return Err(invalid_data("snapshot checksum mismatch"));
+    }
+
+    Ok(pairs)
+}
+
+fn write_record<W: Write>(out: &mut W, bytes: &[u8], checksum: &mut u32) -> io::Result<()> {
+    let len = bytes.len() as u32;
+    out.write_all(&len.to_be_bytes())?;
+    out.write_all(bytes)?;
+    *checksum = checksum.wrapping_add(len);
+    for b in bytes {
+        *checksum = checksum.wrapping_add(*b as u32);
+    }
+    Ok(())
+}
+
+fn read_record(body: &[u8], offset: usize, checksum: &mut u32) -> io::Result<(Vec<u8>, usize)> {
+    if offset + 4 > body.len() {
+        return Err(invalid_data("snapshot truncated"));
+    }
+    let len = u32::from_be_bytes(body[offset..offset + 4].try_into().unwrap()) as usize;
+    let start = offset + 4;
+    let end = start + len;
+    if end > body.len() {
+        return Err(invalid_data("snapshot truncated"));
+    }
+
+    *checksum = checksum.wrapping_add(len as u32);
+    for b in &body[start..end] {
+        *checksum = checksum.wrapping_add(*b as u32);
+    }
+
+    Ok((body[start..end].to_vec(), end))
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
@@ -0,0 +1,137 @@
+//! Channel registry backing `SUBSCRIBE`/`PSUBSCRIBE`/`PUBLISH`.
+//!
+//! Subscribers are registered by connection id against either an exact
+//! channel name or a glob pattern. `publish` fans a payload out to every
+//! matching sink and reports how many subscribers received it.
+
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+use crate::glob::glob_match;
+use crate::resp::RespValue;
+
+pub type SubscriberId = u64;
+
+#[derive(Clone, Default)]
+pub struct PubSub {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    channels: HashMap<String, HashMap<SubscriberId, Sender<RespValue>>>,
+    patterns: HashMap<String, HashMap<SubscriberId, Sender<RespValue>>>,
+}
+
+impl PubSub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self, id: SubscriberId, channel: &str, sink: Sender<RespValue>) {
+        self.inner
+            .lock()
+            .unwrap()
+            .channels
+            .entry(channel.to_string())
+            .or_default()
+            .insert(id, sink);
+    }
+
+    pub fn psubscribe(&self, id: SubscriberId, pattern: &str, sink: Sender<RespValue>) {
+        self.inner
+            .lock()
+            .unwrap()
+            .patterns
+            .entry(pattern.to_string())
+            .or_default()
+            .insert(id, sink);
+    }
+
+    pub fn unsubscribe(&self, id: SubscriberId, channel: &str) {
+        if let Some(subs) = self.inner.lock().unwrap().channels.get_mut(channel) {
+            subs.remove(&id);
+        }
+    }
+
+    /// Drops every subscription (direct and pattern) held by `id`, called
+    /// when a subscriber connection disconnects.
+    pub fn unsubscribe_all(&self, id: SubscriberId) {
+        let mut inner = self.inner.lock().unwrap();
+        for subs in inner.channels.values_mut() {
+            subs.remove(&id);
+        }
+        for subs in inner.patterns.values_mut() {
+            subs.remove(&id);
+        }
+    }
+
+    /// Delivers `payload` to every direct subscriber of `channel` and every
+    /// pattern subscriber whose pattern matches it, returning the number of
+    /// receivers the message was handed to.
+    pub fn publish(&self, channel: &str, payload: &[u8]) -> usize {
+        let inner = self.inner.lock().unwrap();
+        let mut delivered = 0;
+
+        if let Some(subs) = inner.channels.get(channel) {
+            let frame = message_frame(channel, payload);
+            for sink in subs.values() {
+                if sink.send(frame.clone()).is_ok() {
+                    delivered += 1;
+                }
+            }
+        }
+
+        for (pattern, subs) in inner.patterns.iter() {
+            if !glob_match(pattern, channel) {
+                continue;
+            }
+            let frame = pmessage_frame(pattern, channel, payload);
+            for sink in subs.values() {
+                if sink.send(frame.clone()).is_ok() {
+                    delivered += 1;
+                }
+            }
+        }
+
+        delivered
+    }
+}
+
+fn message_frame(channel: &str, payload: &[u8]) -> RespValue {
+    RespValue::Array(vec![
+        RespValue::BulkString(b"message".to_vec()),
+        RespValue::BulkString(channel.as_bytes().to_vec()),
+        RespValue::BulkString(payload.to_vec()),
+    ])
+}
+
+fn pmessage_frame(pattern: &str, channel: &str, payload: &[u8]) -> RespValue {
+    RespValue::Array(vec![
+        RespValue::BulkString(b"pmessage".to_vec()),
+        RespValue::BulkString(pattern.as_bytes().to_vec()),
+        RespValue::BulkString(channel.as_bytes().to_vec()),
+        RespValue::BulkString(payload.to_vec()),
+    ])
+}
+
+fn subscribe_ack_frame(kind: &'static str, name: &str, count: usize) -> RespValue {
+    RespValue::Array(vec![
+        RespValue::BulkString(kind.as_bytes().to_vec()),
+        RespValue::BulkString(name.as_bytes().to_vec()),
+        RespValue::Integer(count as i64),
+    ])
+}
+
+pub fn subscribe_ack(name: &str, count: usize) -> RespValue {
+    subscribe_ack_frame("subscribe", name, count)
+}
+
+pub fn psubscribe_ack(name: &str, count: usize) -> RespValue {
+    subscribe_ack_frame("psubscribe", name, count)
+}
+
+pub fn unsubscribe_ack(name: &str, count: usize) -> RespValue {
+    subscribe_ack_frame("unsubscribe", name, count)
+}
@@ -0,0 +1,164 @@
+//! Minimal RESP (REdis Serialization Protocol) encoder/decoder used by the
+//! connection handler to read commands off the wire and write replies back.
+
+use std::io::{self, BufRead, BufReader, Write};
+
+/// A single RESP value, covering the subset of the protocol redis-less
+/// speaks: simple strings, errors, integers, bulk strings and arrays.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RespValue {
+    SimpleString(String),
+    Error(String),
+    Integer(i64),
+    BulkString(Vec<u8>),
+    NilBulkString,
+    Array(Vec<RespValue>),
+    NilArray,
+}
+
+impl RespValue {
+    pub fn ok() -> Self {
+        RespValue::SimpleString("OK".to_string())
+    }
+
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        match self {
+            RespValue::SimpleString(s) => write!(out, "+{}\r\n", s),
+            RespValue::Error(e) => write!(out, "-{}\r\n", e),
+            RespValue::Integer(i) => write!(out, ":{}\r\n", i),
+            RespValue::BulkString(b) => {
+                write!(out, "${}\r\n", b.len())?;
+                out.write_all(b)?;
+                out.write_all(b"\r\n")
+            }
+            RespValue::NilBulkString => write!(out, "$-1\r\n"),
+            RespValue::Array(items) => {
+                write!(out, "*{}\r\n", items.len())?;
+                for item in items {
+                    item.write_to(out)?;
+                }
+                Ok(())
+            }
+            RespValue::NilArray => write!(out, "*-1\r\n"),
+        }
+    }
+}
+
+/// Reads one RESP command (a `*N\r\n` array of bulk strings) from `reader`.
+///
+/// Returns `Ok(None)` on a clean EOF before any bytes of a new command have
+/// been read, which the caller treats as the peer closing the connection.
+pub fn read_command<R: BufRead>(reader: &mut R) -> io::Result<Option<Vec<Vec<u8>>>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let line = line.trim_end();
+    if !line.starts_with('*') {
+        return Err(invalid_data(format!("expected array, got {:?}", line)));
+    }
+    let arity: usize = line[1..]
+        .parse()
+        .map_err(|_| invalid_data(format!("bad array length: {:?}", line)))?;
+
+    let mut args = Vec::with_capacity(arity);
+    for _ in 0..arity {
+        args.push(read_bulk_string(reader)?);
+    }
+    Ok(Some(args))
+}
+
+/// Reads every command already buffered on `reader`, to support clients
+/// that pipeline several requests in a single `write`. Blocks for the
+/// first command, then keeps parsing out of the reader's internal buffer
+/// for as long as it already holds unconsumed bytes.
+///
+/// This deliberately takes a concrete `BufReader` (rather than a generic
+/// `BufRead`) so it can consult `BufReader::buffer()`, which reports bytes
+/// already sitting in the internal buffer without touching the underlying
+/// reader. `BufRead::fill_buf` looks similar but isn't: once the buffer is
+/// drained it issues a fresh blocking read, which would hang this loop
+/// waiting for a pipelined command that was never sent.
+pub fn read_pipeline<R: io::Read>(reader: &mut BufReader<R>) -> io::Result<Option<Vec<Vec<Vec<u8>>>>> {
+    let mut commands = match read_command(reader)? {
+        Some(command) => vec![command],
+        None => return Ok(None),
+    };
+
+    while !reader.buffer().is_empty() {
+        match read_command(reader)? {
+            Some(command) => commands.push(command),
+            None => break,
+        }
+    }
+
+    Ok(Some(commands))
+}
+
+/// Reads one arbitrary RESP reply (simple string, error, integer, bulk
+/// string, or array), the inverse of `RespValue::write_to`. Used by
+/// `client::InProcessConnection`, which talks to a `Server` without going
+/// through the `redis` crate.
+pub fn read_reply<R: BufRead>(reader: &mut R) -> io::Result<RespValue> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(invalid_data("unexpected eof reading reply".to_string()));
+    }
+    let line = line.trim_end();
+    let (tag, rest) = line.split_at(1);
+    match tag {
+        "+" => Ok(RespValue::SimpleString(rest.to_string())),
+        "-" => Ok(RespValue::Error(rest.to_string())),
+        ":" => rest
+            .parse()
+            .map(RespValue::Integer)
+            .map_err(|_| invalid_data(format!("bad integer: {:?}", rest))),
+        "$" => {
+            let len: i64 = rest
+                .parse()
+                .map_err(|_| invalid_data(format!("bad bulk string length: {:?}", rest)))?;
+            if len < 0 {
+                return Ok(RespValue::NilBulkString);
+            }
+            let mut buf = vec![0u8; len as usize + 2]; // + trailing \r\n
+            reader.read_exact(&mut buf)?;
+            buf.truncate(len as usize);
+            Ok(RespValue::BulkString(buf))
+        }
+        "*" => {
+            let len: i64 = rest
+                .parse()
+                .map_err(|_| invalid_data(format!("bad array length: {:?}", rest)))?;
+            if len < 0 {
+                return Ok(RespValue::NilArray);
+            }
+            let mut items = Vec::with_capacity(len as usize);
+            for _ in 0..len {
+                items.push(read_reply(reader)?);
+            }
+            Ok(RespValue::Array(items))
+        }
+        other => Err(invalid_data(format!("unknown reply tag {:?}", other))),
+    }
+}
+
+fn read_bulk_string<R: BufRead>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let line = line.trim_end();
+    if !line.starts_with('$') {
+        return Err(invalid_data(format!("expected bulk string, got {:?}", line)));
+    }
+    let len: usize = line[1..]
+        .parse()
+        .map_err(|_| invalid_data(format!("bad bulk string length: {:?}", line)))?;
+
+    let mut buf = vec![0u8; len + 2]; // + trailing \r\n
+    reader.read_exact(&mut buf)?;
+    buf.truncate(len);
+    Ok(buf)
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,535 @@
+#[cfg(test)]
+mod tests;
+
+use std::io::{BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use regex::Regex;
+
+use crate::client::InProcessConnection;
+use crate::command::Command;
+use crate::duplex::DuplexStream;
+use crate::glob::glob_match;
+use crate::persistence;
+use crate::pubsub::{psubscribe_ack, subscribe_ack, unsubscribe_ack, PubSub, SubscriberId};
+use crate::resp::{read_pipeline, RespValue};
+use crate::storage::{InMemoryStorage, LockedStorage, Storage};
+use crate::transport::Transport;
+use crate::value::{CallError, FromRedisValue, ToRedisArg};
+
+/// How often the active-expire sweep runs and how many TTL-bearing keys it
+/// samples each time, mirroring Redis's own cadence/sample-size defaults.
+const ACTIVE_EXPIRE_INTERVAL: Duration = Duration::from_millis(100);
+const ACTIVE_EXPIRE_SAMPLE_SIZE: usize = 20;
+
+/// How often the background persistence thread writes a fresh snapshot,
+/// when the server was started with a snapshot path configured.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerState {
+    Started,
+    Stopped,
+}
+
+/// A redis-less server bound to a single TCP port.
+///
+/// Each accepted connection is handled on its own thread; the storage
+/// backend and the pub/sub registry are shared (via internal `Arc`/`Mutex`)
+/// across all of them. Generic over `S: Storage` so a mock backend can be
+/// swapped in for tests; defaults to `InMemoryStorage`, the only backend
+/// redis-less ships.
+pub struct Server<S: Storage = InMemoryStorage> {
+    port: u16,
+    storage: Arc<S>,
+    pubsub: PubSub,
+    snapshot_path: Option<PathBuf>,
+    next_connection_id: Arc<AtomicU64>,
+    listener_thread: Mutex<Option<thread::JoinHandle<()>>>,
+    state: Arc<Mutex<ServerState>>,
+}
+
+impl<S: Storage + 'static> Server<S> {
+    pub fn new(storage: S, port: u16) -> Self {
+        Server {
+            port,
+            storage: Arc::new(storage),
+            pubsub: PubSub::new(),
+            snapshot_path: None,
+            next_connection_id: Arc::new(AtomicU64::new(0)),
+            listener_thread: Mutex::new(None),
+            state: Arc::new(Mutex::new(ServerState::Stopped)),
+        }
+    }
+
+    /// Configures `SAVE`/`BGSAVE` (and startup restore) to use `path` as the
+    /// snapshot file. Chain onto `new`: `Server::new(storage, port).with_snapshot_path(path)`.
+    pub fn with_snapshot_path(mut self, path: PathBuf) -> Self {
+        self.snapshot_path = Some(path);
+        self
+    }
+
+    pub fn start(&self) -> Option<ServerState> {
+        if let Some(path) = &self.snapshot_path {
+            for (key, value) in persistence::load(path).ok()? {
+                self.storage.set(key, value);
+            }
+        }
+
+        let listener = TcpListener::bind(("127.0.0.1", self.port)).ok()?;
+        let storage = self.storage.clone();
+        let pubsub = self.pubsub.clone();
+        let snapshot_path = self.snapshot_path.clone();
+        let next_id = self.next_connection_id.clone();
+        let state = self.state.clone();
+
+        *self.state.lock().unwrap() = ServerState::Started;
+
+        let handle = thread::spawn(move || {
+            for incoming in listener.incoming() {
+                if *state.lock().unwrap() != ServerState::Started {
+                    break;
+                }
+                let Ok(stream) = incoming else { continue };
+                let id = next_id.fetch_add(1, Ordering::SeqCst);
+                let storage = storage.clone();
+                let pubsub = pubsub.clone();
+                let snapshot_path = snapshot_path.clone();
+                thread::spawn(move || handle_connection(id, stream, storage, pubsub, snapshot_path));
+            }
+        });
+
+        *self.listener_thread.lock().unwrap() = Some(handle);
+
+        let eviction_storage = self.storage.clone();
+        let eviction_state = self.state.clone();
+        thread::spawn(move || {
+            while *eviction_state.lock().unwrap() == ServerState::Started {
+                thread::sleep(ACTIVE_EXPIRE_INTERVAL);
+                eviction_storage.evict_sample(ACTIVE_EXPIRE_SAMPLE_SIZE);
+            }
+        });
+
+        if let Some(path) = self.snapshot_path.clone() {
+            let snapshot_storage = self.storage.clone();
+            let snapshot_state = self.state.clone();
+            thread::spawn(move || {
+                while *snapshot_state.lock().unwrap() == ServerState::Started {
+                    thread::sleep(SNAPSHOT_INTERVAL);
+                    let _ = persistence::save(&snapshot_storage.iter(), &path);
+                }
+            });
+        }
+
+        Some(ServerState::Started)
+    }
+
+    pub fn stop(&self) -> Option<ServerState> {
+        *self.state.lock().unwrap() = ServerState::Stopped;
+        // `listener.incoming()` blocks in `accept()`; open a throwaway
+        // connection so the listener thread wakes up, observes the state
+        // change above, and exits.
+        let _ = TcpStream::connect(("127.0.0.1", self.port));
+        Some(ServerState::Stopped)
+    }
+
+    /// Opens a connection to this server over an in-memory duplex pipe
+    /// instead of a TCP socket, so tests (and embedders) can drive it
+    /// without binding a port. Works whether or not `start()` has been
+    /// called, since it talks to the connection handler directly.
+    pub fn connect_in_process(&self) -> InProcessConnection {
+        let (server_end, client_end) = DuplexStream::pair();
+        let id = self.next_connection_id.fetch_add(1, Ordering::SeqCst);
+        let storage = self.storage.clone();
+        let pubsub = self.pubsub.clone();
+        let snapshot_path = self.snapshot_path.clone();
+        thread::spawn(move || handle_connection(id, server_end, storage, pubsub, snapshot_path));
+        InProcessConnection::new(client_end)
+    }
+
+    /// Builds a command from `cmd`/`args`, runs it against storage directly
+    /// (no socket involved), and converts the reply into `T` — the API an
+    /// application embedding redis-less uses to call commands in-process,
+    /// e.g. `server.call::<i64, _>("INCR", &["hits"])`.
+    ///
+    /// Transaction (`MULTI`/`EXEC`/`DISCARD`) and subscription
+    /// (`SUBSCRIBE`/`PSUBSCRIBE`/`UNSUBSCRIBE`) commands aren't supported
+    /// here since they need per-connection state this one-shot call doesn't
+    /// keep; use `connect_in_process` for those. `PUBLISH` works here.
+    pub fn call<T: FromRedisValue, A: ToRedisArg>(&self, cmd: &str, args: &[A]) -> Result<T, CallError> {
+        let mut raw = Vec::with_capacity(args.len() + 1);
+        raw.push(cmd.as_bytes().to_vec());
+        raw.extend(args.iter().map(ToRedisArg::to_redis_arg));
+
+        let command = Command::parse(raw).map_err(|e| CallError::InvalidCommand(e.0))?;
+        match command {
+            Command::Multi | Command::Exec | Command::Discard
+            | Command::Subscribe(_) | Command::PSubscribe(_) | Command::Unsubscribe(_) => {
+                Err(CallError::InvalidCommand(format!(
+                    "{} is not supported via Server::call",
+                    cmd
+                )))
+            }
+            command => {
+                match dispatch(command, &self.storage, &self.pubsub, self.snapshot_path.as_deref()) {
+                    RespValue::Error(message) => Err(CallError::ServerError(message)),
+                    reply => T::from_redis_value(reply).map_err(|e| CallError::Conversion(e.0)),
+                }
+            }
+        }
+    }
+}
+
+fn handle_connection<S: Transport, T: Storage>(
+    id: SubscriberId,
+    stream: S,
+    storage: Arc<T>,
+    pubsub: PubSub,
+    snapshot_path: Option<PathBuf>,
+) {
+    let Ok(reader_stream) = stream.try_clone() else { return };
+    let Ok(writer_stream) = stream.try_clone() else { return };
+    let mut reader = BufReader::new(reader_stream);
+    let mut writer = BufWriter::new(writer_stream);
+
+    let mut subscriber_tx: Option<Sender<RespValue>> = None;
+    let mut subscription_count = 0usize;
+    let mut transaction: Option<Vec<Command>> = None;
+
+    // Pull in every command already buffered on the socket so pipelined
+    // clients (e.g. redis-rs pipelines, batched MGET/MSET) get all their
+    // replies written back together once the batch has been dispatched, one
+    // round trip for the whole batch instead of one per command — see the note below on
+    // why each command still gets its own top-level reply rather than a
+    // single array.
+    while let Ok(Some(commands)) = read_pipeline(&mut reader) {
+        let mut replies = Vec::with_capacity(commands.len());
+        for args in commands {
+            let command = match Command::parse(args) {
+                Ok(command) => command,
+                Err(e) => {
+                    replies.push(RespValue::Error(format!("ERR {}", e.0)));
+                    continue;
+                }
+            };
+
+            match command {
+                Command::Multi => {
+                    if transaction.is_some() {
+                        replies.push(RespValue::Error(
+                            "ERR MULTI calls can not be nested".to_string(),
+                        ));
+                    } else {
+                        transaction = Some(Vec::new());
+                        replies.push(RespValue::ok());
+                    }
+                }
+                Command::Discard => {
+                    if transaction.take().is_some() {
+                        replies.push(RespValue::ok());
+                    } else {
+                        replies.push(RespValue::Error("ERR DISCARD without MULTI".to_string()));
+                    }
+                }
+                Command::Exec => match transaction.take() {
+                    Some(queued) => {
+                        // Take the keyspace lock once for the whole queued
+                        // batch, so no other connection observes it
+                        // half-applied.
+                        let results = storage.with_locked(|keyspace| {
+                            queued
+                                .into_iter()
+                                .map(|queued_command| {
+                                    dispatch_locked(queued_command, keyspace, &pubsub, snapshot_path.as_deref())
+                                })
+                                .collect()
+                        });
+                        replies.push(RespValue::Array(results));
+                    }
+                    None => replies.push(RespValue::Error("ERR EXEC without MULTI".to_string())),
+                },
+                Command::Subscribe(_) | Command::PSubscribe(_) | Command::Unsubscribe(_)
+                    if transaction.is_some() =>
+                {
+                    replies.push(RespValue::Error(
+                        "ERR command not allowed inside a transaction".to_string(),
+                    ));
+                }
+                Command::Subscribe(channels) => {
+                    let tx = subscriber_sink(&mut subscriber_tx, &stream);
+                    for channel in channels {
+                        pubsub.subscribe(id, &channel, tx.clone());
+                        subscription_count += 1;
+                        let _ = tx.send(subscribe_ack(&channel, subscription_count));
+                    }
+                }
+                Command::PSubscribe(patterns) => {
+                    let tx = subscriber_sink(&mut subscriber_tx, &stream);
+                    for pattern in patterns {
+                        pubsub.psubscribe(id, &pattern, tx.clone());
+                        subscription_count += 1;
+                        let _ = tx.send(psubscribe_ack(&pattern, subscription_count));
+                    }
+                }
+                Command::Unsubscribe(channels) => {
+                    for channel in &channels {
+                        pubsub.unsubscribe(id, channel);
+                        subscription_count = subscription_count.saturating_sub(1);
+                    }
+                    if let Some(tx) = &subscriber_tx {
+                        for channel in channels {
+                            let _ = tx.send(unsubscribe_ack(&channel, subscription_count));
+                        }
+                    }
+                }
+                other if transaction.is_some() => {
+                    transaction.as_mut().unwrap().push(other);
+                    replies.push(RespValue::SimpleString("QUEUED".to_string()));
+                }
+                other => replies.push(dispatch(other, &storage, &pubsub, snapshot_path.as_deref())),
+            }
+        }
+
+        // Each queued command gets its own top-level reply, written back
+        // to back in request order — that's what makes this "pipelining"
+        // rather than a single batched call; real Redis never wraps a
+        // pipeline's replies in an array.
+        //
+        // Once this connection has subscribed, `spawn_subscriber_writer`'s
+        // thread owns the only remaining clone of the socket that writes to
+        // it, so a subscribed client's ordinary command replies have to go
+        // through that same channel instead of `writer` — otherwise the two
+        // threads could each be mid-write on their own clone of the stream
+        // at once and interleave partial RESP frames on the wire.
+        match &subscriber_tx {
+            Some(tx) => {
+                for reply in replies {
+                    let _ = tx.send(reply);
+                }
+            }
+            None => {
+                for reply in replies {
+                    let _ = reply.write_to(&mut writer);
+                }
+                let _ = writer.flush();
+            }
+        }
+    }
+
+    pubsub.unsubscribe_all(id);
+}
+
+/// Lazily spawns the dedicated writer thread a subscribed connection uses
+/// to stream `message`/`pmessage` push frames, since published messages can
+/// arrive at any time while this connection's thread is blocked reading the
+/// next command from the socket.
+fn subscriber_sink<'a, S: Transport>(
+    subscriber_tx: &'a mut Option<Sender<RespValue>>,
+    stream: &S,
+) -> &'a Sender<RespValue> {
+    subscriber_tx.get_or_insert_with(|| {
+        let stream = stream.try_clone().expect("clone subscriber stream");
+        spawn_subscriber_writer(stream)
+    })
+}
+
+fn spawn_subscriber_writer<S: Write + Send + 'static>(mut stream: S) -> Sender<RespValue> {
+    let (tx, rx) = mpsc::channel::<RespValue>();
+    thread::spawn(move || {
+        for frame in rx {
+            if frame.write_to(&mut stream).is_err() {
+                break;
+            }
+            if stream.flush().is_err() {
+                break;
+            }
+        }
+    });
+    tx
+}
+
+/// Runs a single command against storage, taking the keyspace lock just for
+/// this one command.
+fn dispatch<S: Storage>(
+    command: Command,
+    storage: &S,
+    pubsub: &PubSub,
+    snapshot_path: Option<&std::path::Path>,
+) -> RespValue {
+    storage.with_locked(|keyspace| dispatch_locked(command, keyspace, pubsub, snapshot_path))
+}
+
+/// The actual command logic, run with the keyspace lock already held.
+/// `EXEC` calls this once per queued command without releasing the lock in
+/// between, which is what makes a transaction atomic with respect to other
+/// connections; `dispatch` is just this plus a single-command lock/unlock.
+fn dispatch_locked<L: LockedStorage>(
+    command: Command,
+    keyspace: &mut L,
+    pubsub: &PubSub,
+    snapshot_path: Option<&std::path::Path>,
+) -> RespValue {
+    match command {
+        Command::Get(key) => match keyspace.get(key.as_bytes()) {
+            Some(value) => RespValue::BulkString(value),
+            None => RespValue::NilBulkString,
+        },
+        Command::Set(key, value) => {
+            keyspace.set(key.into_bytes(), value.into_bytes());
+            RespValue::ok()
+        }
+        Command::Incr(key, delta) => match keyspace.incr(key.as_bytes(), delta) {
+            Ok(value) => RespValue::Integer(value),
+            Err(_) => RespValue::Error("ERR value is not an integer or out of range".to_string()),
+        },
+        Command::Decr(key, delta) => match keyspace.decr(key.as_bytes(), delta) {
+            Ok(value) => RespValue::Integer(value),
+            Err(_) => RespValue::Error("ERR value is not an integer or out of range".to_string()),
+        },
+        Command::Del(key) => {
+            RespValue::Integer(if keyspace.delete(key.as_bytes()) { 1 } else { 0 })
+        }
+        Command::MGet(keys) => RespValue::Array(
+            keys.into_iter()
+                .map(|key| match keyspace.get(key.as_bytes()) {
+                    Some(value) => RespValue::BulkString(value),
+                    None => RespValue::NilBulkString,
+                })
+                .collect(),
+        ),
+        Command::MSet(pairs) => {
+            keyspace.mset(
+                pairs
+                    .into_iter()
+                    .map(|(key, value)| (key.into_bytes(), value.into_bytes()))
+                    .collect(),
+            );
+            RespValue::ok()
+        }
+        Command::Keys(pattern) => RespValue::Array(
+            keyspace
+                .keys()
+                .into_iter()
+                .filter(|key| glob_match(&pattern, &String::from_utf8_lossy(key)))
+                .map(RespValue::BulkString)
+                .collect(),
+        ),
+        Command::Scan { cursor, pattern, count } => {
+            let (next_cursor, page) = keyspace.scan(cursor, count);
+            let keys: Vec<RespValue> = page
+                .into_iter()
+                .filter(|key| {
+                    pattern
+                        .as_ref()
+                        .is_none_or(|p| glob_match(p, &String::from_utf8_lossy(key)))
+                })
+                .map(RespValue::BulkString)
+                .collect();
+            RespValue::Array(vec![
+                RespValue::BulkString(next_cursor.to_string().into_bytes()),
+                RespValue::Array(keys),
+            ])
+        }
+        Command::RgKeys(pattern) => match Regex::new(&pattern) {
+            Ok(re) => RespValue::Array(
+                matching_keys(keyspace, &re)
+                    .into_iter()
+                    .map(RespValue::BulkString)
+                    .collect(),
+            ),
+            Err(e) => RespValue::Error(format!("ERR invalid regex: {}", e)),
+        },
+        Command::RgValues(pattern) => match Regex::new(&pattern) {
+            Ok(re) => RespValue::Array(
+                matching_keys(keyspace, &re)
+                    .into_iter()
+                    .filter_map(|key| keyspace.get(&key))
+                    .map(RespValue::BulkString)
+                    .collect(),
+            ),
+            Err(e) => RespValue::Error(format!("ERR invalid regex: {}", e)),
+        },
+        Command::RgDelete(pattern) => match Regex::new(&pattern) {
+            Ok(re) => {
+                let deleted = matching_keys(keyspace, &re)
+                    .iter()
+                    .filter(|key| keyspace.delete(key))
+                    .count();
+                RespValue::Integer(deleted as i64)
+            }
+            Err(e) => RespValue::Error(format!("ERR invalid regex: {}", e)),
+        },
+        Command::Expire(key, seconds) => {
+            RespValue::Integer(if keyspace.expire(key.as_bytes(), Duration::from_secs(seconds.max(0) as u64)) {
+                1
+            } else {
+                0
+            })
+        }
+        Command::Pexpire(key, millis) => {
+            RespValue::Integer(if keyspace.expire(key.as_bytes(), Duration::from_millis(millis.max(0) as u64)) {
+                1
+            } else {
+                0
+            })
+        }
+        Command::Ttl(key) => match keyspace.ttl(key.as_bytes()) {
+            None => RespValue::Integer(-2),
+            Some(None) => RespValue::Integer(-1),
+            Some(Some(remaining)) => RespValue::Integer(remaining.as_secs() as i64),
+        },
+        Command::Pttl(key) => match keyspace.ttl(key.as_bytes()) {
+            None => RespValue::Integer(-2),
+            Some(None) => RespValue::Integer(-1),
+            Some(Some(remaining)) => RespValue::Integer(remaining.as_millis() as i64),
+        },
+        Command::Persist(key) => {
+            RespValue::Integer(if keyspace.persist(key.as_bytes()) { 1 } else { 0 })
+        }
+        Command::Setex(key, seconds, value) => {
+            keyspace.setex(key.into_bytes(), value.into_bytes(), Duration::from_secs(seconds.max(0) as u64));
+            RespValue::ok()
+        }
+        Command::Publish(channel, payload) => {
+            RespValue::Integer(pubsub.publish(&channel, &payload) as i64)
+        }
+        Command::Save => match snapshot_path {
+            Some(path) => match persistence::save(&keyspace.iter(), path) {
+                Ok(()) => RespValue::ok(),
+                Err(e) => RespValue::Error(format!("ERR {}", e)),
+            },
+            None => RespValue::Error("ERR no snapshot path configured".to_string()),
+        },
+        Command::Bgsave => match snapshot_path {
+            Some(path) => {
+                let pairs = keyspace.iter();
+                let path = path.to_path_buf();
+                thread::spawn(move || {
+                    let _ = persistence::save(&pairs, &path);
+                });
+                RespValue::SimpleString("Background saving started".to_string())
+            }
+            None => RespValue::Error("ERR no snapshot path configured".to_string()),
+        },
+        Command::Multi | Command::Exec | Command::Discard => {
+            unreachable!("transaction control commands are handled before reaching dispatch")
+        }
+        Command::Subscribe(_) | Command::PSubscribe(_) | Command::Unsubscribe(_) => {
+            unreachable!("pub/sub subscription commands are handled before reaching dispatch")
+        }
+    }
+}
+
+fn matching_keys<L: LockedStorage>(keyspace: &mut L, re: &Regex) -> Vec<Vec<u8>> {
+    keyspace
+        .keys()
+        .into_iter()
+        .filter(|key| re.is_match(&String::from_utf8_lossy(key)))
+        .collect()
+}
@@ -1,11 +1,134 @@
-use crate::command::Command;
+use crate::client::InProcessConnection;
+use crate::glob::glob_match;
+use crate::mock::ScriptedMock;
+use crate::resp::RespValue;
 use crate::server::ServerState;
 use crate::storage::in_memory::InMemoryStorage;
+use crate::storage::Storage;
 use crate::Server;
-use redis::{Commands, Connection, RedisWrite, ToRedisArgs};
+use redis::{Commands, Connection, ConnectionLike, RedisResult, RedisWrite, ToRedisArgs, Value};
 use rstest::*;
-use std::fmt::{write, Debug, Display, Formatter, Result};
-use CommandArg::{Int, Str};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use CommandArg::Str;
+
+/// Converts a redis-less `RespValue` reply into the `redis` crate's own
+/// `Value`, so `InProcessConnection` can sit behind `ConnectionLike` and
+/// every `redis::Commands` helper the existing tests already use works
+/// unchanged against it.
+fn to_redis_value(resp: RespValue) -> RedisResult<Value> {
+    match resp {
+        RespValue::SimpleString(s) if s == "OK" => Ok(Value::Okay),
+        RespValue::SimpleString(s) => Ok(Value::Status(s)),
+        RespValue::Error(e) => Err((redis::ErrorKind::ResponseError, "redis-less error", e).into()),
+        RespValue::Integer(i) => Ok(Value::Int(i)),
+        RespValue::BulkString(b) => Ok(Value::Data(b)),
+        RespValue::NilBulkString | RespValue::NilArray => Ok(Value::Nil),
+        RespValue::Array(items) => items
+            .into_iter()
+            .map(to_redis_value)
+            .collect::<RedisResult<Vec<_>>>()
+            .map(Value::Bulk),
+    }
+}
+
+/// Lets `InProcessConnection` stand in for a real `redis::Connection`
+/// wherever a test only needs `ConnectionLike` (i.e. everything but
+/// `Connection::as_pubsub`, which the `redis` crate ties to the concrete
+/// TCP type).
+impl ConnectionLike for InProcessConnection {
+    fn req_packed_command(&mut self, cmd: &[u8]) -> RedisResult<Value> {
+        self.send_packed(cmd)?;
+        to_redis_value(self.read_one()?)
+    }
+
+    fn req_packed_commands(
+        &mut self,
+        cmd: &[u8],
+        offset: usize,
+        count: usize,
+    ) -> RedisResult<Vec<Value>> {
+        self.send_packed(cmd)?;
+        let mut rv = Vec::with_capacity(count);
+        let mut first_err = None;
+        for idx in 0..(offset + count) {
+            match self.read_one().map_err(redis::RedisError::from).and_then(to_redis_value) {
+                Ok(value) if idx >= offset => rv.push(value),
+                Ok(_) => {}
+                Err(e) => {
+                    if first_err.is_none() {
+                        first_err = Some(e);
+                    }
+                }
+            }
+        }
+        first_err.map_or(Ok(rv), Err)
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+
+    fn check_connection(&mut self) -> bool {
+        redis::cmd("PING").query::<String>(self).is_ok()
+    }
+
+    fn is_open(&self) -> bool {
+        true
+    }
+}
+
+/// Either transport a test can drive a `Server` over. `redis::Commands` is
+/// blanket-implemented for anything `ConnectionLike`, so whichever variant
+/// is behind `TestConnection::con`, the existing `.set()`/`.get()`/etc.
+/// call sites don't need to know which one they got.
+enum TestTransport {
+    Tcp(Connection),
+    InProcess(InProcessConnection),
+}
+
+impl ConnectionLike for TestTransport {
+    fn req_packed_command(&mut self, cmd: &[u8]) -> RedisResult<Value> {
+        match self {
+            TestTransport::Tcp(con) => con.req_packed_command(cmd),
+            TestTransport::InProcess(con) => con.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands(
+        &mut self,
+        cmd: &[u8],
+        offset: usize,
+        count: usize,
+    ) -> RedisResult<Vec<Value>> {
+        match self {
+            TestTransport::Tcp(con) => con.req_packed_commands(cmd, offset, count),
+            TestTransport::InProcess(con) => con.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            TestTransport::Tcp(con) => con.get_db(),
+            TestTransport::InProcess(con) => con.get_db(),
+        }
+    }
+
+    fn check_connection(&mut self) -> bool {
+        match self {
+            TestTransport::Tcp(con) => con.check_connection(),
+            TestTransport::InProcess(con) => con.check_connection(),
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        match self {
+            TestTransport::Tcp(con) => con.is_open(),
+            TestTransport::InProcess(con) => con.is_open(),
+        }
+    }
+}
 
 fn get_server_connection(port: u16) -> (Server, Connection) {
     let server = Server::new(InMemoryStorage::new(), port);
@@ -17,14 +140,32 @@ fn get_server_connection(port: u16) -> (Server, Connection) {
 
 struct TestConnection {
     server: Server,
-    con: Connection,
+    con: TestTransport,
 }
 
 impl TestConnection {
+    /// Binds a real TCP port. Only the handful of tests that need a
+    /// genuine socket — a second, independently-obtained connection for
+    /// pub/sub (`Connection::as_pubsub` isn't generic over
+    /// `ConnectionLike`) or a second `Server` reloading a snapshot from
+    /// disk — should reach for this; everything else should use
+    /// `start_in_process`.
     fn start(port: u16) -> Self {
         let (server, con) = get_server_connection(port);
+        TestConnection { server, con: TestTransport::Tcp(con) }
+    }
+    /// Drives a freshly created `Server` over an in-memory duplex pipe
+    /// instead of a socket, so the common case of a single rstest case
+    /// doesn't need a free port.
+    fn start_in_process() -> Self {
+        let server = Server::new(InMemoryStorage::new(), 0);
+        let con = TestTransport::InProcess(server.connect_in_process());
         TestConnection { server, con }
     }
+    fn connect(port: u16) -> Connection {
+        let redis_client = redis::Client::open(format!("redis://127.0.0.1:{}/", port)).unwrap();
+        redis_client.get_connection().unwrap()
+    }
     fn redis_set(&mut self, k: CommandArg, v: CommandArg) {
         let _: () = self.con.set(k, v).unwrap();
     }
@@ -38,6 +179,22 @@ impl TestConnection {
         let res: String = self.con.get(k).unwrap();
         assert_eq!(res, v.to_string());
     }
+    fn redis_mset(&mut self, pairs: Vec<(CommandArg, CommandArg)>) {
+        let _: () = self.con.mset(&pairs).unwrap();
+    }
+    /// `Str("<nil>")` in `expected` stands in for a key that isn't set, so a
+    /// single case can mix present and missing keys.
+    fn test_redis_mget(&mut self, keys: Vec<CommandArg>, expected: Vec<CommandArg>) {
+        let results: Vec<Option<String>> = self.con.get(keys).unwrap();
+        let expected: Vec<Option<String>> = expected
+            .into_iter()
+            .map(|c| match c {
+                Str("<nil>") => None,
+                other => Some(other.to_string()),
+            })
+            .collect();
+        assert_eq!(results, expected);
+    }
     fn halt_running<S: ToString + Display>(&mut self, message: S) {
         self.stop();
         panic!("{}", message);
@@ -75,6 +232,29 @@ impl TestConnection {
                         let (v, k) = (defn.pop(), defn.pop());
                         self.test_redis_get(k.unwrap(), v.unwrap());
                     }
+                    Str("mset") => {
+                        if defn.len() < 3 || (defn.len() - 1) % 2 != 0 {
+                            self.halt_running(format!("mset requires key/value pairs {:?}", defn));
+                        }
+                        let pairs: Vec<(CommandArg, CommandArg)> = defn
+                            .split_off(1)
+                            .chunks(2)
+                            .map(|pair| (pair[0], pair[1]))
+                            .collect();
+                        self.redis_mset(pairs);
+                    }
+                    Str("test_mget") => {
+                        let args = defn.split_off(1);
+                        if args.is_empty() || args.len() % 2 != 0 {
+                            self.halt_running(format!(
+                                "test_mget requires matching keys/expected counts {:?}",
+                                defn
+                            ));
+                        }
+                        let n = args.len() / 2;
+                        let (keys, expected) = args.split_at(n);
+                        self.test_redis_mget(keys.to_vec(), expected.to_vec());
+                    }
                     _ => self.halt_running(format!("unrecognized command definition {:?}", defn)),
                 },
             }
@@ -126,15 +306,15 @@ impl<'a> ToRedisArgs for CommandArg<'a> {
     }
 }
 
-impl<'a> ToString for CommandArg<'a> {
-    fn to_string(&self) -> String {
+impl<'a> Display for CommandArg<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if let Some(inner) = self.int() {
-            return inner.0.to_string();
+            return write!(f, "{}", inner.0);
         }
         if let Some(inner) = self.str() {
-            return inner.0.to_owned();
+            return write!(f, "{}", inner.0);
         }
-        panic!("CommandArg variant unimplemented trait: ToString");
+        panic!("CommandArg variant unimplemented trait: Display");
     }
 }
 
@@ -153,18 +333,13 @@ impl<'a> From<&'a str> for CommandArg<'a> {
 #[macro_export]
 macro_rules! command_args {
     ( $x0:expr $(, $x:expr )+ ) => {{
-        let mut v: Vec<CommandArg> = Vec::new();
-        v.push( $x0.into() );
-        $(
-            v.push( $x.into() );
-        )*
+        let v: Vec<CommandArg> = vec![ $x0.into(), $( $x.into() ),* ];
         v
     }};
 }
 
 #[rstest]
 #[case::incr_decr_by_1(
-    3001,
     vec![
         command_args!["set", "some_number", "12"],
         command_args!["incr", "some_number", 1],
@@ -176,7 +351,6 @@ macro_rules! command_args {
     ]
 )]
 #[case::incr_decr_by_delta(
-    3002,
     vec![
         command_args!["set", "0", 12],
         command_args!["incr", "0", 500],
@@ -192,14 +366,510 @@ macro_rules! command_args {
     ]
 )]
 #[case::set_existent_key(
-    3003,
     vec![
         command_args!["set", 12, "5"],
         command_args!["set", "12", 1200],
         command_args!["test_get", 12, "1200"],
     ]
 )]
-fn test_redis_client(#[case] port: u16, #[case] commands: Vec<Vec<CommandArg>>) {
-    let mut t = TestConnection::start(port);
+#[case::mget_mset_mixed_existence(
+    vec![
+        command_args!["mset", "a", "1", "b", "2"],
+        command_args!["test_mget", "a", "b", "missing", "1", "2", "<nil>"],
+    ]
+)]
+fn test_redis_client(#[case] commands: Vec<Vec<CommandArg>>) {
+    let mut t = TestConnection::start_in_process();
     t.run(commands);
 }
+
+#[rstest]
+fn test_pipelined_commands_reply_in_order() {
+    let mut t = TestConnection::start_in_process();
+
+    let (set_reply, incr_reply, get_reply): (String, i64, String) = redis::pipe()
+        .cmd("SET").arg("counter").arg(1)
+        .cmd("INCR").arg("counter")
+        .cmd("GET").arg("counter")
+        .query(&mut t.con)
+        .unwrap();
+    assert_eq!(set_reply, "OK");
+    assert_eq!(incr_reply, 2);
+    assert_eq!(get_reply, "2");
+
+    t.stop();
+}
+
+#[rstest]
+fn test_keys_glob_match() {
+    let mut t = TestConnection::start_in_process();
+
+    let _: () = t.con.set("user:1", "a").unwrap();
+    let _: () = t.con.set("user:2", "b").unwrap();
+    let _: () = t.con.set("order:1", "c").unwrap();
+
+    let mut keys: Vec<String> = redis::cmd("KEYS").arg("user:*").query(&mut t.con).unwrap();
+    keys.sort();
+    assert_eq!(keys, vec!["user:1".to_string(), "user:2".to_string()]);
+
+    t.stop();
+}
+
+#[rstest]
+fn test_glob_match_does_not_blow_up_on_many_stars() {
+    // A naive recursive matcher backtracks exponentially on patterns like
+    // this one; the two-pointer algorithm stays linear. Both PSUBSCRIBE
+    // (held under PubSub's lock) and KEYS/SCAN MATCH (held under the
+    // keyspace lock) reach `glob_match` once per registration/key, so a
+    // pathological pattern here would freeze every other connection.
+    let pattern = "a*".repeat(25) + "b";
+    let text = "a".repeat(30);
+
+    let start = std::time::Instant::now();
+    assert!(!glob_match(&pattern, &text));
+    assert!(start.elapsed() < std::time::Duration::from_secs(1));
+}
+
+#[rstest]
+fn test_scan_visits_every_key_across_pages() {
+    let mut t = TestConnection::start_in_process();
+
+    for i in 0..5 {
+        let _: () = t.con.set(format!("k{}", i), i).unwrap();
+    }
+
+    let mut seen: Vec<String> = Vec::new();
+    let mut cursor = 0u64;
+    loop {
+        let (next_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("COUNT")
+            .arg(2)
+            .query(&mut t.con)
+            .unwrap();
+        seen.extend(keys);
+        cursor = next_cursor;
+        if cursor == 0 {
+            break;
+        }
+    }
+    seen.sort();
+    assert_eq!(
+        seen,
+        vec!["k0".to_string(), "k1".to_string(), "k2".to_string(), "k3".to_string(), "k4".to_string()]
+    );
+
+    t.stop();
+}
+
+#[rstest]
+fn test_scan_survives_a_delete_before_the_cursor() {
+    let mut t = TestConnection::start_in_process();
+
+    for i in 0..5 {
+        let _: () = t.con.set(format!("k{}", i), i).unwrap();
+    }
+
+    // First page covers k0/k1; deleting k0 used to shift every later key's
+    // index down by one, so resuming at the old cursor skipped whichever
+    // key slid into the hole (here, k2).
+    let (cursor, first_page): (u64, Vec<String>) = redis::cmd("SCAN")
+        .arg(0)
+        .arg("COUNT")
+        .arg(2)
+        .query(&mut t.con)
+        .unwrap();
+    assert_eq!(first_page, vec!["k0".to_string(), "k1".to_string()]);
+
+    let _: i64 = redis::cmd("DEL").arg("k0").query(&mut t.con).unwrap();
+
+    let mut seen: Vec<String> = first_page;
+    let mut cursor = cursor;
+    loop {
+        let (next_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("COUNT")
+            .arg(2)
+            .query(&mut t.con)
+            .unwrap();
+        seen.extend(keys);
+        cursor = next_cursor;
+        if cursor == 0 {
+            break;
+        }
+    }
+    seen.retain(|k| k != "k0");
+    seen.sort();
+    assert_eq!(
+        seen,
+        vec!["k1".to_string(), "k2".to_string(), "k3".to_string(), "k4".to_string()]
+    );
+
+    t.stop();
+}
+
+#[rstest]
+fn test_scan_rejects_a_negative_count_instead_of_panicking() {
+    // `COUNT -1` used to be cast straight to usize, wrapping to usize::MAX
+    // and blowing up `Vec::with_capacity`; that panic poisoned the shared
+    // keyspace mutex and wedged every other connection behind it.
+    let mut t = TestConnection::start_in_process();
+
+    let err = redis::cmd("SCAN")
+        .arg(0)
+        .arg("COUNT")
+        .arg(-1)
+        .query::<(u64, Vec<String>)>(&mut t.con)
+        .unwrap_err();
+    assert!(err.to_string().contains("COUNT must be a positive integer"));
+
+    // The connection (and the storage behind it) must still be usable.
+    let _: () = t.con.set("k", "v").unwrap();
+    let value: String = t.con.get("k").unwrap();
+    assert_eq!(value, "v");
+
+    t.stop();
+}
+
+#[rstest]
+fn test_rgkeys_rgvalues_rgdelete() {
+    let mut t = TestConnection::start_in_process();
+
+    let _: () = t.con.set("session:42", "alice").unwrap();
+    let _: () = t.con.set("session:43", "bob").unwrap();
+    let _: () = t.con.set("cache:1", "ignored").unwrap();
+
+    let mut keys: Vec<String> = redis::cmd("RGKEYS")
+        .arg(r"^session:\d+$")
+        .query(&mut t.con)
+        .unwrap();
+    keys.sort();
+    assert_eq!(keys, vec!["session:42".to_string(), "session:43".to_string()]);
+
+    let mut values: Vec<String> = redis::cmd("RGVALUES")
+        .arg(r"^session:\d+$")
+        .query(&mut t.con)
+        .unwrap();
+    values.sort();
+    assert_eq!(values, vec!["alice".to_string(), "bob".to_string()]);
+
+    let deleted: i64 = redis::cmd("RGDELETE")
+        .arg(r"^session:\d+$")
+        .query(&mut t.con)
+        .unwrap();
+    assert_eq!(deleted, 2);
+
+    let remaining: Vec<String> = redis::cmd("KEYS").arg("*").query(&mut t.con).unwrap();
+    assert_eq!(remaining, vec!["cache:1".to_string()]);
+
+    t.stop();
+}
+
+#[rstest]
+fn test_ttl_reports_missing_and_persistent_keys() {
+    let mut t = TestConnection::start_in_process();
+
+    let missing: i64 = redis::cmd("TTL").arg("nope").query(&mut t.con).unwrap();
+    assert_eq!(missing, -2);
+
+    let _: () = t.con.set("persistent", "v").unwrap();
+    let no_ttl: i64 = redis::cmd("TTL").arg("persistent").query(&mut t.con).unwrap();
+    assert_eq!(no_ttl, -1);
+
+    t.stop();
+}
+
+#[rstest]
+fn test_key_disappears_after_ttl_elapses() {
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    let mut t = TestConnection::start_in_process();
+
+    let _: () = t.con.set("short_lived", "v").unwrap();
+    let _: i32 = redis::cmd("PEXPIRE")
+        .arg("short_lived")
+        .arg(50)
+        .query(&mut t.con)
+        .unwrap();
+
+    sleep(Duration::from_millis(150));
+
+    let value: Option<String> = t.con.get("short_lived").unwrap();
+    assert_eq!(value, None);
+
+    t.stop();
+}
+
+#[rstest]
+fn test_multi_exec_applies_queued_commands_atomically() {
+    let mut t = TestConnection::start_in_process();
+
+    let _: () = t.con.set("counter", 10).unwrap();
+
+    let mut pipe = redis::pipe();
+    pipe.atomic().cmd("SET").arg("counter").arg(0).cmd("INCR").arg("counter");
+    let (set_reply, incr_reply): (String, i64) = pipe.query(&mut t.con).unwrap();
+    assert_eq!(set_reply, "OK");
+    assert_eq!(incr_reply, 1);
+
+    let value: i64 = t.con.get("counter").unwrap();
+    assert_eq!(value, 1);
+
+    t.stop();
+}
+
+#[rstest]
+fn test_discard_drops_queued_commands() {
+    let mut t = TestConnection::start_in_process();
+
+    let _: () = t.con.set("k", "untouched").unwrap();
+    let _: redis::Value = redis::cmd("MULTI").query(&mut t.con).unwrap();
+    let _: redis::Value = redis::cmd("SET").arg("k").arg("changed").query(&mut t.con).unwrap();
+    let _: redis::Value = redis::cmd("DISCARD").query(&mut t.con).unwrap();
+
+    let value: String = t.con.get("k").unwrap();
+    assert_eq!(value, "untouched");
+
+    t.stop();
+}
+
+#[rstest]
+fn test_save_restores_values_into_a_new_server() {
+    let port = 3020;
+    let snapshot_path = std::env::temp_dir().join(format!("redisless-test-{}.rdb", port));
+    let _ = std::fs::remove_file(&snapshot_path);
+
+    let server = Server::new(InMemoryStorage::new(), port).with_snapshot_path(snapshot_path.clone());
+    assert_eq!(server.start(), Some(ServerState::Started));
+    let mut con = TestConnection::connect(port);
+
+    let _: () = con.set("a", "1").unwrap();
+    let _: () = con.set("b", "2").unwrap();
+    let _: redis::Value = redis::cmd("SAVE").query(&mut con).unwrap();
+
+    assert_eq!(server.stop(), Some(ServerState::Stopped));
+
+    let restored = Server::new(InMemoryStorage::new(), port).with_snapshot_path(snapshot_path.clone());
+    assert_eq!(restored.start(), Some(ServerState::Started));
+    let mut restored_con = TestConnection::connect(port);
+
+    let a: String = restored_con.get("a").unwrap();
+    let b: String = restored_con.get("b").unwrap();
+    assert_eq!(a, "1");
+    assert_eq!(b, "2");
+
+    assert_eq!(restored.stop(), Some(ServerState::Stopped));
+    let _ = std::fs::remove_file(&snapshot_path);
+}
+
+#[rstest]
+fn test_publish_delivers_to_subscriber() {
+    let port = 3010;
+    let mut t = TestConnection::start(port);
+    let mut subscriber_con = TestConnection::connect(port);
+
+    let mut pubsub = subscriber_con.as_pubsub();
+    pubsub.subscribe("news").unwrap();
+
+    let receivers: i32 = t.con.publish("news", "hello").unwrap();
+    assert_eq!(receivers, 1);
+
+    let msg = pubsub.get_message().unwrap();
+    assert_eq!(msg.get_channel_name(), "news");
+    let payload: String = msg.get_payload().unwrap();
+    assert_eq!(payload, "hello");
+
+    t.stop();
+}
+
+#[rstest]
+fn test_psubscribe_delivers_to_pattern_subscriber() {
+    let port = 3011;
+    let mut t = TestConnection::start(port);
+    let mut subscriber_con = TestConnection::connect(port);
+
+    let mut pubsub = subscriber_con.as_pubsub();
+    pubsub.psubscribe("news.*").unwrap();
+
+    let receivers: i32 = t.con.publish("news.sports", "goal").unwrap();
+    assert_eq!(receivers, 1);
+
+    let msg = pubsub.get_message().unwrap();
+    assert_eq!(msg.get_channel_name(), "news.sports");
+    let payload: String = msg.get_payload().unwrap();
+    assert_eq!(payload, "goal");
+
+    t.stop();
+}
+
+#[rstest]
+fn test_subscribed_connection_writes_are_not_corrupted_by_concurrent_publishes() {
+    use crate::resp::read_reply;
+    use std::io::{BufReader, Write};
+    use std::net::TcpStream;
+    use std::thread;
+
+    let port = 3022;
+    let mut t = TestConnection::start(port);
+
+    // A raw socket rather than `redis::PubSub`: once subscribed, this
+    // connection keeps issuing ordinary SET commands too (allowed — subscriber
+    // mode doesn't restrict which commands a connection sends), which is exactly
+    // the mix that used to race the push-frame writer thread against the
+    // main loop's writer on two independent clones of the same socket.
+    let mut sub_stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    RespValue::Array(vec![
+        RespValue::BulkString(b"SUBSCRIBE".to_vec()),
+        RespValue::BulkString(b"news".to_vec()),
+    ])
+    .write_to(&mut sub_stream)
+    .unwrap();
+    sub_stream.flush().unwrap();
+    let mut sub_reader = BufReader::new(sub_stream.try_clone().unwrap());
+    read_reply(&mut sub_reader).unwrap(); // subscribe ack
+
+    let publisher = thread::spawn(move || {
+        let mut con = TestConnection::connect(port);
+        for i in 0..300 {
+            let _: i32 = con.publish("news", format!("msg{}", i)).unwrap();
+        }
+    });
+
+    // Interleave a SET per loop with the flood of publishes arriving as
+    // "message" pushes; every read must be either a well-formed push or
+    // the expected "+OK", never a corrupted frame.
+    for i in 0..300 {
+        RespValue::Array(vec![
+            RespValue::BulkString(b"SET".to_vec()),
+            RespValue::BulkString(format!("k{}", i).into_bytes()),
+            RespValue::BulkString(b"v".to_vec()),
+        ])
+        .write_to(&mut sub_stream)
+        .unwrap();
+        sub_stream.flush().unwrap();
+
+        loop {
+            match read_reply(&mut sub_reader).unwrap() {
+                RespValue::SimpleString(s) if s == "OK" => break,
+                RespValue::Array(_) => continue, // an interleaved "message" push
+                other => panic!("corrupted or unexpected reply: {:?}", other),
+            }
+        }
+    }
+
+    publisher.join().unwrap();
+    t.stop();
+}
+
+#[rstest]
+fn test_connect_in_process_runs_commands_without_a_socket() {
+    let server = Server::new(InMemoryStorage::new(), 0);
+    let mut con = server.connect_in_process();
+
+    let set_reply = con.command(&[b"SET", b"k", b"v"]).unwrap();
+    assert_eq!(set_reply, RespValue::ok());
+
+    let get_reply = con.command(&[b"GET", b"k"]).unwrap();
+    assert_eq!(get_reply, RespValue::BulkString(b"v".to_vec()));
+}
+
+#[rstest]
+fn test_call_executes_commands_and_converts_the_reply() {
+    let server = Server::new(InMemoryStorage::new(), 0);
+
+    let set_reply: String = server.call("SET", &["k", "v"]).unwrap();
+    assert_eq!(set_reply, "OK");
+
+    let value: String = server.call("GET", &["k"]).unwrap();
+    assert_eq!(value, "v");
+
+    let missing: Option<String> = server.call("GET", &["missing"]).unwrap();
+    assert_eq!(missing, None);
+
+    let count: i64 = server.call("INCR", &["counter"]).unwrap();
+    assert_eq!(count, 1);
+}
+
+#[rstest]
+fn test_call_rejects_transaction_and_pubsub_commands() {
+    let server = Server::new(InMemoryStorage::new(), 0);
+    let err = server.call::<String, _>("MULTI", &[] as &[&str]).unwrap_err();
+    assert!(matches!(err, crate::CallError::InvalidCommand(_)));
+
+    let err = server.call::<String, _>("SUBSCRIBE", &["news"]).unwrap_err();
+    assert!(matches!(err, crate::CallError::InvalidCommand(_)));
+}
+
+/// A mock `Storage` backend, wrapping `InMemoryStorage` but counting how
+/// many times `with_locked` runs (once per dispatched command, or once per
+/// `MULTI`/`EXEC` batch). Exists to prove `Server<S>` is generic over
+/// `Storage` for real — it goes through the trait for every command rather
+/// than assuming `InMemoryStorage` — not just that it compiles generically.
+struct CountingStorage {
+    inner: InMemoryStorage,
+    lock_count: Arc<AtomicUsize>,
+}
+
+impl Storage for CountingStorage {
+    type Locked = <InMemoryStorage as Storage>::Locked;
+
+    fn set(&self, key: Vec<u8>, value: Vec<u8>) {
+        self.inner.set(key, value)
+    }
+
+    fn iter(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.inner.iter()
+    }
+
+    fn evict_sample(&self, sample_size: usize) -> usize {
+        self.inner.evict_sample(sample_size)
+    }
+
+    fn with_locked<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut Self::Locked) -> T,
+    {
+        self.lock_count.fetch_add(1, Ordering::SeqCst);
+        self.inner.with_locked(f)
+    }
+}
+
+#[rstest]
+fn test_server_is_generic_over_a_mock_storage_backend() {
+    let lock_count = Arc::new(AtomicUsize::new(0));
+    let storage = CountingStorage {
+        inner: InMemoryStorage::new(),
+        lock_count: lock_count.clone(),
+    };
+    let server = Server::new(storage, 0);
+
+    let set_reply: String = server.call("SET", &["k", "v"]).unwrap();
+    assert_eq!(set_reply, "OK");
+
+    let value: String = server.call("GET", &["k"]).unwrap();
+    assert_eq!(value, "v");
+
+    assert_eq!(lock_count.load(Ordering::SeqCst), 2);
+}
+
+#[rstest]
+fn test_scripted_mock_verifies_expected_commands() {
+    let script = vec![
+        (
+            vec![b"GET".to_vec(), b"k".to_vec()],
+            RespValue::BulkString(b"scripted".to_vec()),
+        ),
+        (
+            vec![b"SET".to_vec(), b"k".to_vec(), b"v".to_vec()],
+            RespValue::ok(),
+        ),
+    ];
+    let mut con = ScriptedMock::connect(script);
+
+    let matching_reply = con.command(&[b"GET", b"k"]).unwrap();
+    assert_eq!(matching_reply, RespValue::BulkString(b"scripted".to_vec()));
+
+    let mismatched_reply = con.command(&[b"GET", b"other"]).unwrap();
+    assert!(matches!(mismatched_reply, RespValue::Error(ref m) if m.contains("mock expected")));
+}
@@ -0,0 +1,391 @@
+//! An in-memory, thread-safe key/value store backing the default `Server`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StorageError {
+    /// The stored value for this key is not a valid integer.
+    NotAnInteger,
+}
+
+/// The raw keyspace, guarded by `InMemoryStorage`'s mutex. Its methods never
+/// lock anything themselves, so `MULTI`/`EXEC` can take the lock once (via
+/// `InMemoryStorage::with_locked_keyspace`) and run a whole queued
+/// transaction against it without any other connection observing a
+/// partially-applied batch.
+///
+/// Keys are additionally kept in insertion order behind a monotonic
+/// sequence number, so `SCAN` can use one past the sequence of the last key
+/// it returned as its cursor and resume from there with `BTreeMap::range`.
+/// Unlike a plain `Vec` offset, this is stable across `delete`: removing an
+/// earlier key never changes the sequence number of a later one, so a scan
+/// in progress can't skip a key that was live for its whole duration.
+pub struct Keyspace {
+    values: HashMap<Vec<u8>, Vec<u8>>,
+    order: BTreeMap<u64, Vec<u8>>,
+    key_seq: HashMap<Vec<u8>, u64>,
+    next_seq: u64,
+    expires_at: HashMap<Vec<u8>, Instant>,
+}
+
+impl Keyspace {
+    fn new() -> Self {
+        Keyspace {
+            values: HashMap::new(),
+            order: BTreeMap::new(),
+            key_seq: HashMap::new(),
+            next_seq: 0,
+            expires_at: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn get(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        self.expire_if_needed(key, Instant::now());
+        self.values.get(key).cloned()
+    }
+
+    /// Sets `key`, clearing any TTL it previously had (matching `SET`'s
+    /// behaviour in real Redis).
+    pub(crate) fn set(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        if !self.values.contains_key(&key) {
+            let seq = self.next_seq;
+            self.next_seq += 1;
+            self.order.insert(seq, key.clone());
+            self.key_seq.insert(key.clone(), seq);
+        }
+        self.values.insert(key.clone(), value);
+        self.expires_at.remove(&key);
+    }
+
+    pub(crate) fn setex(&mut self, key: Vec<u8>, value: Vec<u8>, ttl: Duration) {
+        self.set(key.clone(), value);
+        self.expires_at.insert(key, Instant::now() + ttl);
+    }
+
+    pub(crate) fn delete(&mut self, key: &[u8]) -> bool {
+        self.expires_at.remove(key);
+        if self.values.remove(key).is_some() {
+            if let Some(seq) = self.key_seq.remove(key) {
+                self.order.remove(&seq);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn mset(&mut self, pairs: Vec<(Vec<u8>, Vec<u8>)>) {
+        for (key, value) in pairs {
+            self.set(key, value);
+        }
+    }
+
+    pub(crate) fn incr(&mut self, key: &[u8], delta: i64) -> Result<i64, StorageError> {
+        self.expire_if_needed(key, Instant::now());
+        let current = match self.values.get(key) {
+            Some(bytes) => parse_int(bytes)?,
+            None => 0,
+        };
+        let updated = current + delta;
+        self.set(key.to_vec(), updated.to_string().into_bytes());
+        Ok(updated)
+    }
+
+    pub(crate) fn decr(&mut self, key: &[u8], delta: i64) -> Result<i64, StorageError> {
+        self.incr(key, -delta)
+    }
+
+    /// All keys currently set, in insertion order.
+    pub(crate) fn keys(&mut self) -> Vec<Vec<u8>> {
+        self.expire_all_if_needed(Instant::now());
+        self.order.values().cloned().collect()
+    }
+
+    pub(crate) fn scan(&mut self, cursor: usize, count: usize) -> (usize, Vec<Vec<u8>>) {
+        self.expire_all_if_needed(Instant::now());
+        let mut remaining = self.order.range(cursor as u64..);
+        let mut page = Vec::with_capacity(count);
+        let mut last_seq = None;
+        for (seq, key) in remaining.by_ref().take(count) {
+            last_seq = Some(*seq);
+            page.push(key.clone());
+        }
+        let next_cursor = match last_seq {
+            Some(seq) if remaining.next().is_some() => seq + 1,
+            _ => 0,
+        };
+        (next_cursor as usize, page)
+    }
+
+    pub(crate) fn expire(&mut self, key: &[u8], ttl: Duration) -> bool {
+        let now = Instant::now();
+        self.expire_if_needed(key, now);
+        if self.values.contains_key(key) {
+            self.expires_at.insert(key.to_vec(), now + ttl);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn persist(&mut self, key: &[u8]) -> bool {
+        self.expire_if_needed(key, Instant::now());
+        self.expires_at.remove(key).is_some()
+    }
+
+    pub(crate) fn ttl(&mut self, key: &[u8]) -> Option<Option<Duration>> {
+        let now = Instant::now();
+        self.expire_if_needed(key, now);
+        if !self.values.contains_key(key) {
+            return None;
+        }
+        Some(
+            self.expires_at
+                .get(key)
+                .map(|at| at.saturating_duration_since(now)),
+        )
+    }
+
+    /// Every live key/value pair, in insertion order.
+    pub(crate) fn iter(&mut self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.expire_all_if_needed(Instant::now());
+        self.order
+            .values()
+            .filter_map(|key| self.values.get(key).map(|value| (key.clone(), value.clone())))
+            .collect()
+    }
+
+    pub(crate) fn evict_sample(&mut self, sample_size: usize) -> usize {
+        let now = Instant::now();
+        let sample: Vec<Vec<u8>> = self.expires_at.keys().take(sample_size).cloned().collect();
+        let mut evicted = 0;
+        for key in sample {
+            if self.is_expired(&key, now) {
+                self.delete(&key);
+                evicted += 1;
+            }
+        }
+        evicted
+    }
+
+    fn is_expired(&self, key: &[u8], now: Instant) -> bool {
+        self.expires_at.get(key).is_some_and(|at| *at <= now)
+    }
+
+    /// Lazily drops `key` if its TTL has passed, so every read sees it as
+    /// absent without waiting for the active eviction sweep.
+    fn expire_if_needed(&mut self, key: &[u8], now: Instant) {
+        if self.is_expired(key, now) {
+            self.delete(key);
+        }
+    }
+
+    fn expire_all_if_needed(&mut self, now: Instant) {
+        let expired: Vec<Vec<u8>> = self
+            .expires_at
+            .iter()
+            .filter(|(_, at)| **at <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            self.delete(&key);
+        }
+    }
+}
+
+/// The default storage backend: an `Arc<Mutex<Keyspace>>` shared between
+/// every connection handler thread.
+#[derive(Clone)]
+pub struct InMemoryStorage {
+    data: Arc<Mutex<Keyspace>>,
+}
+
+impl InMemoryStorage {
+    pub fn new() -> Self {
+        InMemoryStorage {
+            data: Arc::new(Mutex::new(Keyspace::new())),
+        }
+    }
+
+    /// Grants exclusive access to the raw keyspace for the duration of `f`,
+    /// taking the lock only once. `MULTI`/`EXEC` uses this to run a whole
+    /// queued transaction atomically with respect to other connections.
+    pub(crate) fn with_locked_keyspace<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut Keyspace) -> T,
+    {
+        f(&mut self.lock())
+    }
+
+    /// A single bad command shouldn't be able to wedge the whole server: if
+    /// some other thread panicked while holding the lock, recover the guard
+    /// instead of propagating the poison (and panicking every thread after
+    /// it, forever).
+    fn lock(&self) -> MutexGuard<'_, Keyspace> {
+        self.data.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.lock().get(key)
+    }
+
+    pub fn set(&self, key: Vec<u8>, value: Vec<u8>) {
+        self.lock().set(key, value)
+    }
+
+    /// `SETEX key seconds value`: sets the value and its TTL in one step.
+    pub fn setex(&self, key: Vec<u8>, value: Vec<u8>, ttl: Duration) {
+        self.lock().setex(key, value, ttl)
+    }
+
+    pub fn delete(&self, key: &[u8]) -> bool {
+        self.lock().delete(key)
+    }
+
+    /// Sets every pair while holding the lock once, so no other connection
+    /// can observe a partially-applied `MSET`.
+    pub fn mset(&self, pairs: Vec<(Vec<u8>, Vec<u8>)>) {
+        self.lock().mset(pairs)
+    }
+
+    pub fn incr(&self, key: &[u8], delta: i64) -> Result<i64, StorageError> {
+        self.lock().incr(key, delta)
+    }
+
+    pub fn decr(&self, key: &[u8], delta: i64) -> Result<i64, StorageError> {
+        self.lock().decr(key, delta)
+    }
+
+    /// All keys currently set, in insertion order.
+    pub fn keys(&self) -> Vec<Vec<u8>> {
+        self.lock().keys()
+    }
+
+    /// Returns up to `count` keys starting at `cursor` (the insertion
+    /// sequence number to resume from), plus the next cursor to pass back
+    /// in, which is `0` once the scan is exhausted.
+    pub fn scan(&self, cursor: usize, count: usize) -> (usize, Vec<Vec<u8>>) {
+        self.lock().scan(cursor, count)
+    }
+
+    /// Sets the expiry on `key`. Returns `false` if the key doesn't exist.
+    pub fn expire(&self, key: &[u8], ttl: Duration) -> bool {
+        self.lock().expire(key, ttl)
+    }
+
+    /// Clears `key`'s TTL, if any. Returns `false` if it had none (or the
+    /// key is missing).
+    pub fn persist(&self, key: &[u8]) -> bool {
+        self.lock().persist(key)
+    }
+
+    /// `None` if the key is missing, `Some(None)` if it exists with no TTL,
+    /// `Some(Some(remaining))` otherwise.
+    pub fn ttl(&self, key: &[u8]) -> Option<Option<Duration>> {
+        self.lock().ttl(key)
+    }
+
+    /// Mirrors Redis's active-expire cycle: samples up to `sample_size` keys
+    /// that carry a TTL and evicts any that have already passed it, so
+    /// expired keys that are never read don't linger in memory forever.
+    pub fn evict_sample(&self, sample_size: usize) -> usize {
+        self.lock().evict_sample(sample_size)
+    }
+
+    /// Every live key/value pair, for snapshotting to disk.
+    pub fn iter(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.lock().iter()
+    }
+}
+
+impl crate::storage::Storage for InMemoryStorage {
+    type Locked = Keyspace;
+
+    fn set(&self, key: Vec<u8>, value: Vec<u8>) {
+        InMemoryStorage::set(self, key, value)
+    }
+
+    fn iter(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        InMemoryStorage::iter(self)
+    }
+
+    fn evict_sample(&self, sample_size: usize) -> usize {
+        InMemoryStorage::evict_sample(self, sample_size)
+    }
+
+    fn with_locked<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut Keyspace) -> T,
+    {
+        self.with_locked_keyspace(f)
+    }
+}
+
+impl crate::storage::LockedStorage for Keyspace {
+    fn get(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        Keyspace::get(self, key)
+    }
+
+    fn set(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        Keyspace::set(self, key, value)
+    }
+
+    fn setex(&mut self, key: Vec<u8>, value: Vec<u8>, ttl: Duration) {
+        Keyspace::setex(self, key, value, ttl)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> bool {
+        Keyspace::delete(self, key)
+    }
+
+    fn mset(&mut self, pairs: Vec<(Vec<u8>, Vec<u8>)>) {
+        Keyspace::mset(self, pairs)
+    }
+
+    fn incr(&mut self, key: &[u8], delta: i64) -> Result<i64, StorageError> {
+        Keyspace::incr(self, key, delta)
+    }
+
+    fn decr(&mut self, key: &[u8], delta: i64) -> Result<i64, StorageError> {
+        Keyspace::decr(self, key, delta)
+    }
+
+    fn keys(&mut self) -> Vec<Vec<u8>> {
+        Keyspace::keys(self)
+    }
+
+    fn scan(&mut self, cursor: usize, count: usize) -> (usize, Vec<Vec<u8>>) {
+        Keyspace::scan(self, cursor, count)
+    }
+
+    fn expire(&mut self, key: &[u8], ttl: Duration) -> bool {
+        Keyspace::expire(self, key, ttl)
+    }
+
+    fn persist(&mut self, key: &[u8]) -> bool {
+        Keyspace::persist(self, key)
+    }
+
+    fn ttl(&mut self, key: &[u8]) -> Option<Option<Duration>> {
+        Keyspace::ttl(self, key)
+    }
+
+    fn iter(&mut self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        Keyspace::iter(self)
+    }
+}
+
+impl Default for InMemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_int(bytes: &[u8]) -> Result<i64, StorageError> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or(StorageError::NotAnInteger)
+}
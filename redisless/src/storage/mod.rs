@@ -0,0 +1,83 @@
+pub mod in_memory;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use in_memory::{InMemoryStorage, StorageError};
+
+/// The operations a storage backend must support to sit behind `Server`.
+///
+/// `Server<S>` is generic over this trait, `InMemoryStorage` being the
+/// backend it defaults to and the only one redis-less ships, but promoting
+/// these operations into a trait means persistence (see `crate::persistence`)
+/// can be written against any backend, and tests can swap in a mock one.
+pub trait Storage: Send + Sync {
+    /// The exclusively-locked view `with_locked` hands to its closure. Holds
+    /// every operation a single command (or a queued `MULTI`/`EXEC` batch)
+    /// needs, so the whole batch can run under one lock without any other
+    /// connection observing it half-applied.
+    type Locked: LockedStorage;
+
+    fn set(&self, key: Vec<u8>, value: Vec<u8>);
+
+    /// Every live key/value pair, for snapshotting to disk.
+    fn iter(&self) -> Vec<(Vec<u8>, Vec<u8>)>;
+
+    /// Mirrors Redis's active-expire cycle: samples up to `sample_size` keys
+    /// that carry a TTL and evicts any that have already passed it.
+    fn evict_sample(&self, sample_size: usize) -> usize;
+
+    /// Grants exclusive access to the backend for the duration of `f`, taking
+    /// the lock only once. `MULTI`/`EXEC` uses this to run a whole queued
+    /// transaction atomically with respect to other connections.
+    fn with_locked<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut Self::Locked) -> T;
+}
+
+/// The per-command operations available once `Storage::with_locked` has
+/// taken the lock. Mirrors `in_memory::Keyspace`'s own interface, which is
+/// the only type that implements it today.
+pub trait LockedStorage {
+    fn get(&mut self, key: &[u8]) -> Option<Vec<u8>>;
+    fn set(&mut self, key: Vec<u8>, value: Vec<u8>);
+    fn setex(&mut self, key: Vec<u8>, value: Vec<u8>, ttl: Duration);
+    fn delete(&mut self, key: &[u8]) -> bool;
+    fn mset(&mut self, pairs: Vec<(Vec<u8>, Vec<u8>)>);
+    fn incr(&mut self, key: &[u8], delta: i64) -> Result<i64, StorageError>;
+    fn decr(&mut self, key: &[u8], delta: i64) -> Result<i64, StorageError>;
+    /// All keys currently set, in insertion order.
+    fn keys(&mut self) -> Vec<Vec<u8>>;
+    fn scan(&mut self, cursor: usize, count: usize) -> (usize, Vec<Vec<u8>>);
+    fn expire(&mut self, key: &[u8], ttl: Duration) -> bool;
+    fn persist(&mut self, key: &[u8]) -> bool;
+    fn ttl(&mut self, key: &[u8]) -> Option<Option<Duration>>;
+    /// Every live key/value pair, for `SAVE`/`BGSAVE`.
+    fn iter(&mut self) -> Vec<(Vec<u8>, Vec<u8>)>;
+}
+
+/// `Server` shares its backend across connection threads behind an `Arc`, so
+/// it calls `Storage` through `Arc<S>` rather than `S` directly; this just
+/// forwards each method to the wrapped backend.
+impl<S: Storage> Storage for Arc<S> {
+    type Locked = S::Locked;
+
+    fn set(&self, key: Vec<u8>, value: Vec<u8>) {
+        (**self).set(key, value)
+    }
+
+    fn iter(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        (**self).iter()
+    }
+
+    fn evict_sample(&self, sample_size: usize) -> usize {
+        (**self).evict_sample(sample_size)
+    }
+
+    fn with_locked<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut Self::Locked) -> T,
+    {
+        (**self).with_locked(f)
+    }
+}
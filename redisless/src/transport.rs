@@ -0,0 +1,19 @@
+//! Abstracts the duplex byte stream a connection is handled over, so the
+//! same connection-handling loop in `server` can run atop a real
+//! `TcpStream` or the in-memory `DuplexStream` used by
+//! `Server::connect_in_process`.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+pub(crate) trait Transport: Read + Write + Send + 'static {
+    fn try_clone(&self) -> io::Result<Self>
+    where
+        Self: Sized;
+}
+
+impl Transport for TcpStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+}
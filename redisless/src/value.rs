@@ -0,0 +1,113 @@
+//! Conversions between `RespValue` replies and native Rust types, shared by
+//! the network dispatch path (which only ever produces `RespValue`s) and
+//! `Server::call`, the embedded API for invoking commands programmatically
+//! without going through a socket or the `redis` crate.
+
+use crate::resp::RespValue;
+
+/// Something that can be sent as one argument of a `Command`.
+pub trait ToRedisArg {
+    fn to_redis_arg(&self) -> Vec<u8>;
+}
+
+impl ToRedisArg for &str {
+    fn to_redis_arg(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl ToRedisArg for String {
+    fn to_redis_arg(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl ToRedisArg for i64 {
+    fn to_redis_arg(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+}
+
+impl ToRedisArg for &[u8] {
+    fn to_redis_arg(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+/// An error converting a `RespValue` reply into the type a caller asked for.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConversionError(pub String);
+
+/// Something a `RespValue` reply can be converted into. Deliberately not
+/// implemented for `u8`, so the blanket `Vec<T>` impl below can't overlap
+/// with the dedicated `Vec<u8>` impl (which treats a bulk string as raw
+/// bytes rather than an array of one-byte replies).
+pub trait FromRedisValue: Sized {
+    fn from_redis_value(value: RespValue) -> Result<Self, ConversionError>;
+}
+
+impl FromRedisValue for i64 {
+    fn from_redis_value(value: RespValue) -> Result<Self, ConversionError> {
+        match value {
+            RespValue::Integer(i) => Ok(i),
+            RespValue::BulkString(bytes) => std::str::from_utf8(&bytes)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| ConversionError("reply is not an integer".to_string())),
+            other => Err(ConversionError(format!("cannot convert {:?} to i64", other))),
+        }
+    }
+}
+
+impl FromRedisValue for String {
+    fn from_redis_value(value: RespValue) -> Result<Self, ConversionError> {
+        match value {
+            RespValue::BulkString(bytes) => {
+                String::from_utf8(bytes).map_err(|_| ConversionError("reply is not valid utf-8".to_string()))
+            }
+            RespValue::SimpleString(s) => Ok(s),
+            other => Err(ConversionError(format!("cannot convert {:?} to String", other))),
+        }
+    }
+}
+
+impl FromRedisValue for Vec<u8> {
+    fn from_redis_value(value: RespValue) -> Result<Self, ConversionError> {
+        match value {
+            RespValue::BulkString(bytes) => Ok(bytes),
+            other => Err(ConversionError(format!("cannot convert {:?} to Vec<u8>", other))),
+        }
+    }
+}
+
+impl<T: FromRedisValue> FromRedisValue for Option<T> {
+    fn from_redis_value(value: RespValue) -> Result<Self, ConversionError> {
+        match value {
+            RespValue::NilBulkString | RespValue::NilArray => Ok(None),
+            other => T::from_redis_value(other).map(Some),
+        }
+    }
+}
+
+impl<T: FromRedisValue> FromRedisValue for Vec<T> {
+    fn from_redis_value(value: RespValue) -> Result<Self, ConversionError> {
+        match value {
+            RespValue::Array(items) => items.into_iter().map(T::from_redis_value).collect(),
+            other => Err(ConversionError(format!("cannot convert {:?} to Vec<_>", other))),
+        }
+    }
+}
+
+/// An error from `Server::call`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CallError {
+    /// `cmd`/`args` didn't parse into a `Command` (unknown name, wrong
+    /// arity), or named a command `call` doesn't support (transactions and
+    /// `SUBSCRIBE`/`PSUBSCRIBE`/`UNSUBSCRIBE`, which need connection state
+    /// `call` doesn't have).
+    InvalidCommand(String),
+    /// The server executed the command and replied with a RESP error.
+    ServerError(String),
+    /// The reply couldn't be converted into the requested type.
+    Conversion(String),
+}